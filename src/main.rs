@@ -1,80 +1,454 @@
-fn generate_data(buffer: &mut [f32], rate: f32, phase: &mut f32) {
+fn generate_data(buffer: &mut [f32], rate: f32, channels: u32, phase: &mut f32) {
     const FREQUENCY: f32 = 440.0;
-    for i in buffer {
-        *i = (*phase * std::f32::consts::TAU * FREQUENCY / rate).sin();
+    for frame in buffer.chunks_mut(channels as usize) {
+        let sample = (*phase * std::f32::consts::TAU * FREQUENCY / rate).sin();
+        frame.fill(sample);
         *phase += 1.0;
+        if *phase > rate {
+            *phase -= rate;
+        }
     }
-    if *phase > rate {
-        *phase -= rate;
+}
+
+/// What to do about an errno from an ALSA I/O call, per the standard ALSA
+/// recovery dance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum XrunAction {
+    /// `-EAGAIN` isn't an XRUN at all, just the non-blocking PCM saying
+    /// "nothing to do right now" - retry later rather than recovering.
+    Retry,
+    /// `-EPIPE`/`-ESTRPIPE`: a genuine XRUN or stream suspend, recoverable
+    /// via `snd_pcm_recover`.
+    Recover,
+    /// Anything else is fatal.
+    Fatal,
+}
+
+/// Classifies an errno from `readi`/`writei`/`avail_update`/`avail`/`delay`
+/// into what [`recover_from_xrun`] should do about it. Pulled out as a pure
+/// function so the classification can be unit tested without a real PCM.
+fn classify_xrun_errno(errno: i32) -> XrunAction {
+    match errno {
+        libc::EAGAIN => XrunAction::Retry,
+        libc::EPIPE | libc::ESTRPIPE => XrunAction::Recover,
+        _ => XrunAction::Fatal,
     }
 }
 
-pub struct AlsaPlayback {
-    pcm: alsa::PCM,
-    async_fd: tokio::io::unix::AsyncFd<std::os::fd::RawFd>,
-    poll_fd: libc::pollfd,
-    rate: f32,
+/// Attempts to recover a PCM from an XRUN or stream suspend reported by
+/// `readi`/`writei`, per the standard ALSA recovery dance. While recovering a
+/// suspended stream, `snd_pcm_recover` can itself return `-EAGAIN` until the
+/// stream actually resumes, so we keep calling it until it either succeeds or
+/// fails for real.
+fn recover_from_xrun(pcm: &alsa::PCM, err: alsa::Error) -> std::io::Result<()> {
+    let errno = err.errno();
+    match classify_xrun_errno(errno) {
+        XrunAction::Retry => return Ok(()),
+        XrunAction::Fatal => return Err(std::io::Error::other(format!("ALSA I/O failed: {err}"))),
+        XrunAction::Recover => {}
+    }
+
+    eprintln!("warning: ALSA I/O error ({err}), attempting recovery");
+
+    loop {
+        match pcm.recover(errno, true) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.errno() == libc::EAGAIN => continue,
+            Err(e) => return Err(std::io::Error::other(format!("ALSA recovery failed: {e}"))),
+        }
+    }
+}
+
+/// Runs an ALSA call that can itself fail with an XRUN/suspend/`EAGAIN`
+/// (`avail_update`, `avail`, `delay`, ...) and routes any such error through
+/// [`recover_from_xrun`] instead of the caller `expect`/`unwrap`-ing it.
+/// `Ok(None)` means the error was recovered from (or was a plain `EAGAIN`)
+/// and the caller should treat this attempt as having made no progress;
+/// `Ok(Some(value))` is the normal success path.
+fn recoverable<T>(pcm: &alsa::PCM, result: alsa::Result<T>) -> std::io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => recover_from_xrun(pcm, err).map(|()| None),
+    }
+}
+
+/// `avail()` and `writei`/`readi` deal in frames, but the buffers threaded
+/// through `AlsaWriter`/`AlsaReader` are flat interleaved sample slices, so
+/// every frame count needs multiplying by the channel count before it's used
+/// to size or account for a slice of samples. Returns the number of samples
+/// (a whole number of frames, never more than `available_samples`) that can
+/// be read or written right now.
+fn frames_to_samples(frames: alsa::pcm::Frames, channels: usize, available_samples: usize) -> usize {
+    let frames = std::cmp::max(frames, 0) as usize;
+    std::cmp::min(frames, available_samples / channels) * channels
 }
 
-impl AlsaPlayback {
-    pub fn new(device: &str) -> Self {
-        let pcm = alsa::PCM::new(device, alsa::Direction::Playback, true)
-            .expect("Failed to open device for playback");
+/// The subset of readiness ALSA asked for, independent of any particular
+/// async runtime's own notion of "interest".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcmInterest {
+    Readable,
+    Writable,
+    Error,
+}
 
-        let hwparams = alsa::pcm::HwParams::any(&pcm).unwrap();
-        hwparams
-            .set_access(alsa::pcm::Access::RWInterleaved)
-            .unwrap();
-        hwparams.set_format(alsa::pcm::Format::FloatLE).unwrap();
+/// Maps the raw `poll(2)` events ALSA asked for on a PCM's descriptor to the
+/// [`PcmInterest`] a reactor backend should actually wait on. Shared by
+/// `AlsaPlayback` and `AlsaCapture`, since either direction can end up
+/// waiting on a status pipe rather than the audio device itself.
+fn interest_from_pollfd(poll_fd: &libc::pollfd) -> PcmInterest {
+    if poll_fd.events & libc::POLLIN != 0 {
+        PcmInterest::Readable
+    } else if poll_fd.events & libc::POLLOUT != 0 {
+        PcmInterest::Writable
+    } else if poll_fd.events & libc::POLLERR != 0 {
+        PcmInterest::Error
+    } else {
+        panic!("Unknown interest");
+    }
+}
 
-        hwparams
-            .set_rate_near(44100, alsa::ValueOr::Nearest)
-            .unwrap();
+/// A guard obtained once the PCM fd is ready, used to attempt an I/O
+/// operation without losing the registered readiness if the fd turns out not
+/// to actually be ready (a spurious wakeup).
+pub trait PcmReadyGuard {
+    /// The `Err(())` case just means "spurious wakeup, try again" - there's
+    /// nothing more to say about it, so a real error type would be pure
+    /// ceremony.
+    #[allow(clippy::result_unit_err)]
+    fn try_io<T>(
+        &mut self,
+        f: impl FnOnce() -> std::io::Result<T>,
+    ) -> Result<std::io::Result<T>, ()>;
 
-        hwparams.set_channels(1).unwrap();
+    /// Tells the reactor this guard's readiness was stale: the operation it
+    /// gated ran but made no actual progress (e.g. `avail() == 0` right
+    /// after an XRUN was reset). Some backends cache readiness until it's
+    /// explicitly cleared, so without this the next `ready`/`poll_ready`
+    /// call would hand back the same stale guard instead of waiting for a
+    /// fresh edge, starving the task of a wakeup.
+    fn clear_ready(&mut self);
+}
 
-        pcm.hw_params(&hwparams).expect("Failed to initialise ALSA");
+/// Abstracts over the async reactor used to wait for the ALSA PCM file
+/// descriptor to become ready, so the crate isn't hard-wired to tokio.
+///
+/// ALSA sometimes asks us to wait on `POLLIN` even for a write-only PCM
+/// (it's actually waiting on a status pipe, not the audio device itself) -
+/// whichever backend implements this trait must wait on the interest it's
+/// actually given and nothing else; the remapping back to what ALSA meant
+/// happens afterwards via `Descriptors::revents`.
+pub trait AsyncPcmFd: Sized {
+    type Guard<'a>: PcmReadyGuard
+    where
+        Self: 'a;
 
-        let rate = hwparams.get_rate().expect("Couldn't get rate") as f32;
+    fn new(fd: std::os::fd::RawFd) -> std::io::Result<Self>;
+
+    // Only implemented within this crate, so the lack of an auto-trait bound
+    // on the returned future (e.g. `Send`) isn't a public-API concern here.
+    #[allow(async_fn_in_trait)]
+    async fn ready(&self, interest: PcmInterest) -> std::io::Result<Self::Guard<'_>>;
+
+    fn poll_ready(
+        &self,
+        interest: PcmInterest,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<Self::Guard<'_>>>;
+}
+
+/// The default reactor backend, built on `tokio::io::unix::AsyncFd`.
+pub struct TokioReactor(tokio::io::unix::AsyncFd<std::os::fd::RawFd>);
+
+pub struct TokioGuard<'a>(tokio::io::unix::AsyncFdReadyGuard<'a, std::os::fd::RawFd>);
+
+impl PcmReadyGuard for TokioGuard<'_> {
+    fn try_io<T>(
+        &mut self,
+        f: impl FnOnce() -> std::io::Result<T>,
+    ) -> Result<std::io::Result<T>, ()> {
+        self.0.try_io(|_fd| f()).map_err(|_would_block| ())
+    }
+
+    fn clear_ready(&mut self) {
+        self.0.clear_ready();
+    }
+}
+
+impl AsyncPcmFd for TokioReactor {
+    type Guard<'a> = TokioGuard<'a>;
+
+    fn new(fd: std::os::fd::RawFd) -> std::io::Result<Self> {
+        Ok(Self(tokio::io::unix::AsyncFd::new(fd)?))
+    }
+
+    async fn ready(&self, interest: PcmInterest) -> std::io::Result<Self::Guard<'_>> {
+        let interest = match interest {
+            PcmInterest::Readable => tokio::io::Interest::READABLE,
+            PcmInterest::Writable => tokio::io::Interest::WRITABLE,
+            PcmInterest::Error => tokio::io::Interest::ERROR,
+        };
+        Ok(TokioGuard(self.0.ready(interest).await?))
+    }
+
+    fn poll_ready(
+        &self,
+        interest: PcmInterest,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<Self::Guard<'_>>> {
+        let poll = match interest {
+            PcmInterest::Readable => self.0.poll_read_ready(cx),
+            PcmInterest::Writable => self.0.poll_write_ready(cx),
+            // AsyncFd has no generic by-Interest poll_ready; its fixed
+            // READABLE|WRITABLE registration already reports error
+            // conditions as read readiness, so mirror the `ready()` path's
+            // use of `Interest::ERROR` (which mio itself maps to READABLE)
+            // by racing the two polls.
+            PcmInterest::Error => match self.0.poll_read_ready(cx) {
+                std::task::Poll::Pending => self.0.poll_write_ready(cx),
+                ready => ready,
+            },
+        };
+        match poll {
+            std::task::Poll::Ready(Ok(guard)) => std::task::Poll::Ready(Ok(TokioGuard(guard))),
+            std::task::Poll::Ready(Err(err)) => std::task::Poll::Ready(Err(err)),
+            std::task::Poll::Pending => std::task::Poll::Pending,
+        }
+    }
+}
+
+/// A non-owning handle to a raw PCM fd. `async-io`'s `Async<T>` requires
+/// `T: AsFd`, but the fd's lifecycle is already owned by `AlsaPlayback`'s or
+/// `AlsaCapture`'s `alsa::PCM`, so this just borrows it rather than handing
+/// over ownership (and closing it) the way `Async<OwnedFd>` would.
+#[cfg(feature = "async-io")]
+struct BorrowedPcmFd(std::os::fd::RawFd);
+
+#[cfg(feature = "async-io")]
+impl std::os::fd::AsFd for BorrowedPcmFd {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        // Safety: the fd outlives this wrapper, since it's owned by the
+        // alsa::PCM that handed it to us via Descriptors::get.
+        unsafe { std::os::fd::BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/// An alternative reactor backend built on `async-io`'s `Async`, wrapping the
+/// PCM's raw fd in a non-owning `BorrowedPcmFd`, for use under smol or any other `async-io`-based executor in place of tokio.
+#[cfg(feature = "async-io")]
+pub struct AsyncIoReactor(async_io::Async<BorrowedPcmFd>);
+
+#[cfg(feature = "async-io")]
+pub struct AsyncIoGuard;
+
+#[cfg(feature = "async-io")]
+impl PcmReadyGuard for AsyncIoGuard {
+    fn try_io<T>(
+        &mut self,
+        f: impl FnOnce() -> std::io::Result<T>,
+    ) -> Result<std::io::Result<T>, ()> {
+        // Mirror TokioGuard: a WouldBlock means ALSA's revents said it
+        // wasn't really ready (the status-pipe POLLIN-for-write case), so
+        // the caller should wait for another readiness notification rather
+        // than treating it as a hard I/O error.
+        match f() {
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => Err(()),
+            result => Ok(result),
+        }
+    }
+
+    fn clear_ready(&mut self) {
+        // async-io's readable()/writable() futures wait for a fresh
+        // OS-level notification on every poll; there's no cached "still
+        // ready" bit here that needs clearing.
+    }
+}
+
+#[cfg(feature = "async-io")]
+impl AsyncPcmFd for AsyncIoReactor {
+    type Guard<'a> = AsyncIoGuard;
+
+    fn new(fd: std::os::fd::RawFd) -> std::io::Result<Self> {
+        Ok(Self(async_io::Async::new(BorrowedPcmFd(fd))?))
+    }
+
+    async fn ready(&self, interest: PcmInterest) -> std::io::Result<Self::Guard<'_>> {
+        match interest {
+            PcmInterest::Readable => self.0.readable().await?,
+            PcmInterest::Writable => self.0.writable().await?,
+            // async-io has no dedicated "wait for error" primitive, but on
+            // the epoll/kqueue backends it wraps, a POLLERR condition also
+            // wakes up both read and write readiness, so racing the two
+            // catches it without a backend-specific escape hatch.
+            PcmInterest::Error => {
+                match futures::future::select(Box::pin(self.0.readable()), Box::pin(self.0.writable()))
+                    .await
+                {
+                    futures::future::Either::Left((result, _)) => result?,
+                    futures::future::Either::Right((result, _)) => result?,
+                }
+            }
+        }
+        Ok(AsyncIoGuard)
+    }
+
+    fn poll_ready(
+        &self,
+        interest: PcmInterest,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<Self::Guard<'_>>> {
+        let poll = match interest {
+            PcmInterest::Readable => self.0.poll_readable(cx),
+            PcmInterest::Writable => self.0.poll_writable(cx),
+            PcmInterest::Error => match self.0.poll_readable(cx) {
+                std::task::Poll::Pending => self.0.poll_writable(cx),
+                ready => ready,
+            },
+        };
+        match poll {
+            std::task::Poll::Ready(Ok(())) => std::task::Poll::Ready(Ok(AsyncIoGuard)),
+            std::task::Poll::Ready(Err(err)) => std::task::Poll::Ready(Err(err)),
+            std::task::Poll::Pending => std::task::Poll::Pending,
+        }
+    }
+}
+
+/// Configuration for opening an [`AlsaPlayback`], in place of the previous
+/// hardcoded mono/`FloatLE`/44100Hz setup. The rate, and the period and buffer
+/// sizes when given, are requested with ALSA's `set_*_near` and may be
+/// adjusted; period and buffer sizes left unset are chosen by ALSA. The
+/// channel count and sample format must be supported exactly, otherwise
+/// opening fails with an error. Call [`AlsaPlayback::params`] afterwards to
+/// see what was actually granted.
+#[derive(Debug, Clone, Copy)]
+pub struct AlsaConfig {
+    channels: u32,
+    rate: u32,
+    format: alsa::pcm::Format,
+    period_size: Option<alsa::pcm::Frames>,
+    buffer_size: Option<alsa::pcm::Frames>,
+}
+
+impl Default for AlsaConfig {
+    fn default() -> Self {
+        Self {
+            channels: 1,
+            rate: 44100,
+            format: alsa::pcm::Format::FloatLE,
+            period_size: None,
+            buffer_size: None,
+        }
+    }
+}
+
+impl AlsaConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn channels(mut self, channels: u32) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    pub fn rate(mut self, rate: u32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    pub fn format(mut self, format: alsa::pcm::Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn period_size(mut self, frames: alsa::pcm::Frames) -> Self {
+        self.period_size = Some(frames);
+        self
+    }
+
+    pub fn buffer_size(mut self, frames: alsa::pcm::Frames) -> Self {
+        self.buffer_size = Some(frames);
+        self
+    }
+}
+
+/// The values ALSA actually negotiated for a hardware configuration, which
+/// may differ from what was requested in [`AlsaConfig`].
+#[derive(Debug, Clone, Copy)]
+pub struct AlsaParams {
+    pub rate: u32,
+    pub channels: u32,
+    pub period_size: alsa::pcm::Frames,
+    pub buffer_size: alsa::pcm::Frames,
+}
+
+pub struct AlsaPlayback<R = TokioReactor>
+where
+    R: AsyncPcmFd,
+{
+    pcm: alsa::PCM,
+    async_pcm_fd: R,
+    poll_fd: libc::pollfd,
+    params: AlsaParams,
+}
+
+impl<R: AsyncPcmFd> AlsaPlayback<R> {
+    pub fn new(device: &str, config: AlsaConfig) -> alsa::Result<Self> {
+        let pcm = alsa::PCM::new(device, alsa::Direction::Playback, true)?;
+
+        let hwparams = alsa::pcm::HwParams::any(&pcm)?;
+        hwparams.set_access(alsa::pcm::Access::RWInterleaved)?;
+        hwparams.set_format(config.format)?;
+        hwparams.set_rate_near(config.rate, alsa::ValueOr::Nearest)?;
+        hwparams.set_channels(config.channels)?;
+        if let Some(period_size) = config.period_size {
+            hwparams.set_period_size_near(period_size, alsa::ValueOr::Nearest)?;
+        }
+        if let Some(buffer_size) = config.buffer_size {
+            hwparams.set_buffer_size_near(buffer_size)?;
+        }
+
+        pcm.hw_params(&hwparams)?;
+
+        let params = AlsaParams {
+            rate: hwparams.get_rate()?,
+            channels: hwparams.get_channels()?,
+            period_size: hwparams.get_period_size()?,
+            buffer_size: hwparams.get_buffer_size()?,
+        };
 
         drop(hwparams);
 
         let fds = alsa::poll::Descriptors::get(&pcm).expect("Couldn't get ALSA PCM FDs");
         let poll_fd = fds.first().unwrap();
-        let async_fd = tokio::io::unix::AsyncFd::new(poll_fd.fd).expect("couldn't get async fd");
+        let async_pcm_fd = R::new(poll_fd.fd).expect("couldn't set up reactor for PCM fd");
 
-        Self {
+        Ok(Self {
             pcm,
-            async_fd,
+            async_pcm_fd,
             poll_fd: *poll_fd,
-            rate,
-        }
+            params,
+        })
     }
 
     #[inline]
     fn get_rate(&self) -> f32 {
-        self.rate
+        self.params.rate as f32
     }
 
-    fn get_interest(&self) -> tokio::io::Interest {
-        use tokio::io::Interest;
+    #[inline]
+    pub fn params(&self) -> AlsaParams {
+        self.params
+    }
 
+    fn get_interest(&self) -> PcmInterest {
         // Even for write only use like this, alsa often requires read events, since it's asking
         // you to wait on a status pipe rather than the underlying audio device.
-
-        if self.poll_fd.events & libc::POLLIN != 0 {
-            Interest::READABLE
-        } else if self.poll_fd.events & libc::POLLOUT != 0 {
-            Interest::WRITABLE
-        } else if self.poll_fd.events & libc::POLLERR != 0 {
-            Interest::ERROR
-        } else {
-            panic!("Unknown interest");
-        }
+        interest_from_pollfd(&self.poll_fd)
     }
 }
 
-impl std::fmt::Debug for AlsaPlayback {
+impl<R: AsyncPcmFd> std::fmt::Debug for AlsaPlayback<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut output = alsa::Output::buffer_open().expect("couldn't open output");
         self.pcm.dump(&mut output).expect("dump failed");
@@ -82,45 +456,32 @@ impl std::fmt::Debug for AlsaPlayback {
     }
 }
 
-pub struct AlsaWriter<'p, Sample>(&'p AlsaPlayback, alsa::pcm::IO<'p, Sample>)
+pub struct AlsaWriter<'p, Sample, R = TokioReactor>(&'p AlsaPlayback<R>, alsa::pcm::IO<'p, Sample>)
 where
-    Sample: alsa::pcm::IoFormat;
+    Sample: alsa::pcm::IoFormat,
+    R: AsyncPcmFd;
 
-impl<'p, Sample: alsa::pcm::IoFormat> AlsaWriter<'p, Sample> {
-    pub fn new(playback: &'p AlsaPlayback) -> Self {
+impl<'p, Sample: alsa::pcm::IoFormat, R: AsyncPcmFd> AlsaWriter<'p, Sample, R> {
+    pub fn new(playback: &'p AlsaPlayback<R>) -> Self {
         Self(playback, playback.pcm.io_checked().expect("Wrong format"))
     }
 
-    pub async fn write(&self, to_send: &[Sample]) -> std::io::Result<usize> {
-        let interest = self.0.get_interest();
-        let mut guard = self
-            .0
-            .async_fd
-            .ready(interest)
-            .await
-            .expect("Failed to get asyncfd guard");
-        let io_result = guard.try_io(|_fd| {
-            // As this is an example program for async i/o only, we are not handling XRUN or other
-            // failures, just aborting to keep the code clear to understand the primary point.
-
+    fn try_write_once(
+        &self,
+        guard: &mut R::Guard<'_>,
+        to_send: &[Sample],
+    ) -> Result<std::io::Result<usize>, ()> {
+        guard.try_io(|| {
             //let current_state = pcm.state();
             //assert_eq!(current_state, alsa::pcm::State::Running);
 
             let fds = [libc::pollfd {
                 fd: self.0.poll_fd.fd,
                 events: self.0.poll_fd.events,
-                revents: if interest.is_readable() {
-                    libc::POLLIN
-                } else {
-                    0
-                } | if interest.is_writable() {
-                    libc::POLLOUT
-                } else {
-                    0
-                } | if interest.is_error() {
-                    libc::POLLERR
-                } else {
-                    0
+                revents: match self.0.get_interest() {
+                    PcmInterest::Readable => libc::POLLIN,
+                    PcmInterest::Writable => libc::POLLOUT,
+                    PcmInterest::Error => libc::POLLERR,
                 },
             }];
 
@@ -130,60 +491,90 @@ impl<'p, Sample: alsa::pcm::IoFormat> AlsaWriter<'p, Sample> {
             let flags = alsa::poll::Descriptors::revents(&self.0.pcm, &fds)
                 .expect("Failed to alsa revents");
 
-            self.0
-                .pcm
-                .avail_update()
-                .expect("Failed to update ALSA avail");
+            let Some(()) = recoverable(&self.0.pcm, self.0.pcm.avail_update().map(|_| ()))? else {
+                return Ok(0);
+            };
 
-            let delay = self.0.pcm.delay().expect("couldn't get delay");
+            let Some(delay) = recoverable(&self.0.pcm, self.0.pcm.delay())? else {
+                return Ok(0);
+            };
             let rate = self.0.get_rate();
             let delay_ms = 1000.0 * delay as f32 / rate;
 
             println!("flags={flags:?}  delay={delay_ms}ms");
             if flags.contains(alsa::poll::Flags::OUT) {
-                let frames = self.0.pcm.avail().unwrap();
-                let count = self
-                    .1
-                    .writei(&to_send[..std::cmp::min(frames as usize, to_send.len())])
-                    .expect("write failed");
-                println!("{count}");
-                Ok(count)
+                let Some(frames) = recoverable(&self.0.pcm, self.0.pcm.avail())? else {
+                    return Ok(0);
+                };
+                let channels = self.0.params.channels as usize;
+                let sample_count = frames_to_samples(frames, channels, to_send.len());
+                if sample_count == 0 {
+                    return Ok(0);
+                }
+                let chunk = &to_send[..sample_count];
+                match self.1.writei(chunk) {
+                    Ok(frames_written) => {
+                        println!("{frames_written}");
+                        Ok(frames_written * channels)
+                    }
+                    // A single underrun shouldn't abort playback: recover and let the
+                    // caller re-issue the write for whatever frames didn't make it out.
+                    Err(err) => recover_from_xrun(&self.0.pcm, err).map(|()| 0),
+                }
             } else {
                 // ALSA is NOT ready for writing according to its internal logic (alsa_flags).
-                // Return WouldBlock to prevent the spin: this tells Tokio to re-poll the FD.
+                // Return WouldBlock so the caller waits for readiness again instead of spinning.
                 Err(std::io::Error::new(
                     std::io::ErrorKind::WouldBlock,
                     "ALSA not ready for write according to its revents flags",
                 ))
             }
-        });
+        })
+    }
+
+    pub async fn write(&self, to_send: &[Sample]) -> std::io::Result<usize> {
+        let interest = self.0.get_interest();
+        let mut guard = self
+            .0
+            .async_pcm_fd
+            .ready(interest)
+            .await
+            .expect("Failed to get reactor readiness guard");
 
-        match io_result {
-            Ok(Ok(count)) => Ok(count),
-            Ok(Err(err)) => Err(err),
-            Err(_would_block) => Ok(0),
+        match self.try_write_once(&mut guard, to_send) {
+            Ok(result) => result,
+            Err(()) => Ok(0),
         }
     }
 }
 
 const BUFFER_SIZE: usize = 65536;
 
-pub struct AlsaBufferedWriter<'p, Sample>
+pub struct AlsaBufferedWriter<'p, Sample, R = TokioReactor>
 where
     Sample: alsa::pcm::IoFormat,
+    R: AsyncPcmFd,
 {
-    writer: AlsaWriter<'p, Sample>,
+    writer: AlsaWriter<'p, Sample, R>,
     buffer: std::collections::VecDeque<Sample>,
+    // The readiness guard the reactor handed us the last time we polled it,
+    // kept around across `poll_ready`/`poll_flush` calls after a write that
+    // moved samples, so the next call can write again straight away. After
+    // a spurious wakeup or a write that moved nothing it is dropped and
+    // interest is registered afresh.
+    pending_guard: Option<R::Guard<'p>>,
 }
 
-impl<'p, Sample> AlsaBufferedWriter<'p, Sample>
+impl<'p, Sample, R> AlsaBufferedWriter<'p, Sample, R>
 where
     Sample: alsa::pcm::IoFormat,
+    R: AsyncPcmFd,
 {
-    pub fn new(writer: AlsaWriter<'p, Sample>) -> Self {
+    pub fn new(writer: AlsaWriter<'p, Sample, R>) -> Self {
         Self {
             writer,
             buffer: Default::default(),
+            pending_guard: None,
         }
     }
 
@@ -215,32 +606,91 @@ where
         self.flush().await
         // TODO: Finish the stream
     }
+
+    /// Drains the buffer down below `threshold`, resuming from whatever
+    /// readiness guard is already pending rather than re-registering
+    /// interest (and thereby risking a lost wakeup) on every call.
+    fn poll_drain_while(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        threshold: usize,
+    ) -> std::task::Poll<std::io::Result<()>>
+    where
+        R::Guard<'p>: Unpin,
+        Sample: Unpin,
+    {
+        let this = self.get_mut();
+        loop {
+            if this.buffer.len() < threshold {
+                return std::task::Poll::Ready(Ok(()));
+            }
+
+            if this.pending_guard.is_none() {
+                let playback: &'p AlsaPlayback<R> = this.writer.0;
+                let interest = playback.get_interest();
+                this.pending_guard = match playback.async_pcm_fd.poll_ready(interest, cx) {
+                    std::task::Poll::Ready(Ok(guard)) => Some(guard),
+                    std::task::Poll::Ready(Err(err)) => return std::task::Poll::Ready(Err(err)),
+                    std::task::Poll::Pending => return std::task::Poll::Pending,
+                };
+            }
+
+            let mut guard = this.pending_guard.take().expect("just populated above");
+            let (to_send, _) = this.buffer.as_slices();
+            match this.writer.try_write_once(&mut guard, to_send) {
+                Ok(Ok(0)) => {
+                    // The reactor said we were ready and the write "succeeded",
+                    // but no frames actually moved (avail() == 0, or XRUN
+                    // recovery just reset state without freeing any room).
+                    // `try_io` only clears cached readiness on a WouldBlock
+                    // error, and this wasn't one, so without an explicit
+                    // clear_ready the next poll_ready would just hand back
+                    // the same stale guard and register no waker at all,
+                    // deadlocking the task. Clear it and loop back around to
+                    // re-register interest properly instead.
+                    guard.clear_ready();
+                    continue;
+                }
+                Ok(Ok(count)) => {
+                    this.buffer.drain(..count);
+                    this.pending_guard = Some(guard);
+                }
+                Ok(Err(err)) => return std::task::Poll::Ready(Err(err)),
+                Err(()) => {
+                    // The reactor said the fd was ready but ALSA disagreed (a
+                    // spurious wakeup); drop the stale guard and re-register
+                    // interest on the next loop iteration.
+                }
+            }
+        }
+    }
 }
 
-impl<'p, Sample> futures::sink::Sink<Sample> for AlsaBufferedWriter<'p, Sample>
+impl<'p, Sample, R> futures::sink::Sink<Sample> for AlsaBufferedWriter<'p, Sample, R>
 where
     Sample: alsa::pcm::IoFormat + Unpin,
+    R: AsyncPcmFd,
+    R::Guard<'p>: Unpin,
 {
     type Error = std::io::Error;
 
     fn poll_ready(
-        mut self: std::pin::Pin<&mut Self>,
+        self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
-        let p = std::pin::pin!(self.ready());
-        p.poll(cx)
+        self.poll_drain_while(cx, BUFFER_SIZE)
     }
 
     fn start_send(self: std::pin::Pin<&mut Self>, item: Sample) -> Result<(), Self::Error> {
-        std::pin::pin!(self).send(item)
+        self.get_mut().buffer.push_back(item);
+        Ok(())
     }
 
     fn poll_flush(
-        mut self: std::pin::Pin<&mut Self>,
+        self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
-        let p = std::pin::pin!(self.flush());
-        p.poll(cx)
+        self.poll_drain_while(cx, 1)
     }
 
     fn poll_close(
@@ -251,12 +701,266 @@ where
     }
 }
 
+pub struct AlsaCapture<R = TokioReactor>
+where
+    R: AsyncPcmFd,
+{
+    pcm: alsa::PCM,
+    async_pcm_fd: R,
+    poll_fd: libc::pollfd,
+    params: AlsaParams,
+}
+
+impl<R: AsyncPcmFd> AlsaCapture<R> {
+    pub fn new(device: &str, config: AlsaConfig) -> alsa::Result<Self> {
+        let pcm = alsa::PCM::new(device, alsa::Direction::Capture, true)?;
+
+        let hwparams = alsa::pcm::HwParams::any(&pcm)?;
+        hwparams.set_access(alsa::pcm::Access::RWInterleaved)?;
+        hwparams.set_format(config.format)?;
+        hwparams.set_rate_near(config.rate, alsa::ValueOr::Nearest)?;
+        hwparams.set_channels(config.channels)?;
+        if let Some(period_size) = config.period_size {
+            hwparams.set_period_size_near(period_size, alsa::ValueOr::Nearest)?;
+        }
+        if let Some(buffer_size) = config.buffer_size {
+            hwparams.set_buffer_size_near(buffer_size)?;
+        }
+
+        pcm.hw_params(&hwparams)?;
+
+        let params = AlsaParams {
+            rate: hwparams.get_rate()?,
+            channels: hwparams.get_channels()?,
+            period_size: hwparams.get_period_size()?,
+            buffer_size: hwparams.get_buffer_size()?,
+        };
+
+        drop(hwparams);
+
+        let fds = alsa::poll::Descriptors::get(&pcm).expect("Couldn't get ALSA PCM FDs");
+        let poll_fd = fds.first().unwrap();
+        let async_pcm_fd = R::new(poll_fd.fd).expect("couldn't set up reactor for PCM fd");
+
+        pcm.start()?;
+
+        Ok(Self {
+            pcm,
+            async_pcm_fd,
+            poll_fd: *poll_fd,
+            params,
+        })
+    }
+
+    #[inline]
+    pub fn params(&self) -> AlsaParams {
+        self.params
+    }
+
+    fn get_interest(&self) -> PcmInterest {
+        interest_from_pollfd(&self.poll_fd)
+    }
+}
+
+impl<R: AsyncPcmFd> std::fmt::Debug for AlsaCapture<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut output = alsa::Output::buffer_open().expect("couldn't open output");
+        self.pcm.dump(&mut output).expect("dump failed");
+        f.write_str(&format!("{output}"))
+    }
+}
+
+pub struct AlsaReader<'p, Sample, R = TokioReactor>(&'p AlsaCapture<R>, alsa::pcm::IO<'p, Sample>)
+where
+    Sample: alsa::pcm::IoFormat,
+    R: AsyncPcmFd;
+
+impl<'p, Sample: alsa::pcm::IoFormat, R: AsyncPcmFd> AlsaReader<'p, Sample, R> {
+    pub fn new(capture: &'p AlsaCapture<R>) -> Self {
+        Self(capture, capture.pcm.io_checked().expect("Wrong format"))
+    }
+
+    fn try_read_once(
+        &self,
+        guard: &mut R::Guard<'_>,
+        buffer: &mut [Sample],
+    ) -> Result<std::io::Result<usize>, ()> {
+        guard.try_io(|| {
+            let fds = [libc::pollfd {
+                fd: self.0.poll_fd.fd,
+                events: self.0.poll_fd.events,
+                revents: match self.0.get_interest() {
+                    PcmInterest::Readable => libc::POLLIN,
+                    PcmInterest::Writable => libc::POLLOUT,
+                    PcmInterest::Error => libc::POLLERR,
+                },
+            }];
+
+            // Same remapping dance as AlsaWriter::write: wait on whatever ALSA
+            // actually asked for, then ask ALSA what it meant by it.
+            let flags = alsa::poll::Descriptors::revents(&self.0.pcm, &fds)
+                .expect("Failed to alsa revents");
+
+            let Some(()) = recoverable(&self.0.pcm, self.0.pcm.avail_update().map(|_| ()))? else {
+                return Ok(0);
+            };
+
+            if flags.contains(alsa::poll::Flags::IN) {
+                let Some(frames) = recoverable(&self.0.pcm, self.0.pcm.avail())? else {
+                    return Ok(0);
+                };
+                let channels = self.0.params.channels as usize;
+                let sample_count = frames_to_samples(frames, channels, buffer.len());
+                if sample_count == 0 {
+                    return Ok(0);
+                }
+                match self.1.readi(&mut buffer[..sample_count]) {
+                    Ok(frames_read) => Ok(frames_read * channels),
+                    Err(err) => recover_from_xrun(&self.0.pcm, err).map(|()| 0),
+                }
+            } else {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::WouldBlock,
+                    "ALSA not ready for read according to its revents flags",
+                ))
+            }
+        })
+    }
+
+    pub async fn read(&self, buffer: &mut [Sample]) -> std::io::Result<usize> {
+        let interest = self.0.get_interest();
+        let mut guard = self
+            .0
+            .async_pcm_fd
+            .ready(interest)
+            .await
+            .expect("Failed to get reactor readiness guard");
+
+        match self.try_read_once(&mut guard, buffer) {
+            Ok(result) => result,
+            Err(()) => Ok(0),
+        }
+    }
+}
+
+pub struct AlsaBufferedReader<'p, Sample, R = TokioReactor>
+where
+    Sample: alsa::pcm::IoFormat + Default + Copy,
+    R: AsyncPcmFd,
+{
+    reader: AlsaReader<'p, Sample, R>,
+    buffer: std::collections::VecDeque<Sample>,
+    scratch: Vec<Sample>,
+    // Mirrors AlsaBufferedWriter::pending_guard: the readiness guard the
+    // reactor handed us last poll, kept around after a read that returned
+    // samples so the next poll can read again straight away. After a
+    // spurious wakeup or an empty read it is dropped and interest is
+    // registered afresh.
+    pending_guard: Option<R::Guard<'p>>,
+}
+
+impl<'p, Sample, R> AlsaBufferedReader<'p, Sample, R>
+where
+    Sample: alsa::pcm::IoFormat + Default + Copy,
+    R: AsyncPcmFd,
+{
+    pub fn new(reader: AlsaReader<'p, Sample, R>) -> Self {
+        Self {
+            reader,
+            buffer: Default::default(),
+            scratch: vec![Sample::default(); BUFFER_SIZE],
+            pending_guard: None,
+        }
+    }
+
+    /// Fills `buffer` with at least one sample, resuming from whatever
+    /// readiness guard is already pending rather than re-registering
+    /// interest (and thereby risking a lost wakeup) on every call. ALSA
+    /// capture has no notion of end-of-stream, so this only ever completes
+    /// with samples in `buffer` or a fatal error; it never reports EOF.
+    fn poll_fill(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>>
+    where
+        R::Guard<'p>: Unpin,
+        Sample: Unpin,
+    {
+        let this = self.get_mut();
+        loop {
+            if !this.buffer.is_empty() {
+                return std::task::Poll::Ready(Ok(()));
+            }
+
+            if this.pending_guard.is_none() {
+                let capture: &'p AlsaCapture<R> = this.reader.0;
+                let interest = capture.get_interest();
+                this.pending_guard = match capture.async_pcm_fd.poll_ready(interest, cx) {
+                    std::task::Poll::Ready(Ok(guard)) => Some(guard),
+                    std::task::Poll::Ready(Err(err)) => return std::task::Poll::Ready(Err(err)),
+                    std::task::Poll::Pending => return std::task::Poll::Pending,
+                };
+            }
+
+            let mut guard = this.pending_guard.take().expect("just populated above");
+            match this.reader.try_read_once(&mut guard, &mut this.scratch) {
+                Ok(Ok(0)) => {
+                    // The reactor said we were ready and the read "succeeded",
+                    // but no frames actually arrived (a spurious status-pipe
+                    // wakeup, or an overrun that recover_from_xrun just reset).
+                    // `try_io` only clears cached readiness on a WouldBlock
+                    // error, and this wasn't one, so clear it explicitly and
+                    // loop back around to re-register interest instead of
+                    // deadlocking on a stale guard with no waker registered
+                    // (and definitely don't report this as end-of-stream).
+                    guard.clear_ready();
+                    continue;
+                }
+                Ok(Ok(count)) => {
+                    this.buffer.extend(this.scratch[..count].iter().copied());
+                    this.pending_guard = Some(guard);
+                }
+                Ok(Err(err)) => return std::task::Poll::Ready(Err(err)),
+                Err(()) => {
+                    // The reactor said the fd was ready but ALSA disagreed (a
+                    // spurious wakeup); drop the stale guard and re-register
+                    // interest on the next loop iteration.
+                }
+            }
+        }
+    }
+}
+
+impl<'p, Sample, R> futures::stream::Stream for AlsaBufferedReader<'p, Sample, R>
+where
+    Sample: alsa::pcm::IoFormat + Default + Copy + Unpin,
+    R: AsyncPcmFd,
+    R::Guard<'p>: Unpin,
+{
+    type Item = std::io::Result<Sample>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        match self.as_mut().poll_fill(cx) {
+            std::task::Poll::Ready(Ok(())) => {
+                std::task::Poll::Ready(self.buffer.pop_front().map(Ok))
+            }
+            std::task::Poll::Ready(Err(err)) => std::task::Poll::Ready(Some(Err(err))),
+            std::task::Poll::Pending => std::task::Poll::Pending,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     const DEVICE_NAME: &str = "default";
     let mut phase: f32 = 0.0;
 
-    let alsa = AlsaPlayback::new(DEVICE_NAME);
+    let alsa = AlsaPlayback::<TokioReactor>::new(DEVICE_NAME, AlsaConfig::default())
+        .expect("Failed to initialise ALSA playback");
+    let params = alsa.params();
 
     let mut data = [0.0; 65536];
 
@@ -268,7 +972,7 @@ async fn main() {
         let mut sink = AlsaBufferedWriter::new(writer);
 
         loop {
-            generate_data(&mut data, alsa.get_rate(), &mut phase);
+            generate_data(&mut data, params.rate as f32, params.channels, &mut phase);
             println!("phase={phase}");
 
             for i in data {
@@ -279,7 +983,7 @@ async fn main() {
     } else {
         let mut buffered = AlsaBufferedWriter::new(writer);
         loop {
-            generate_data(&mut data, alsa.get_rate(), &mut phase);
+            generate_data(&mut data, params.rate as f32, params.channels, &mut phase);
             println!("phase={phase}");
 
             for i in data {
@@ -289,3 +993,83 @@ async fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_xrun_errno_eagain_is_retry() {
+        assert_eq!(classify_xrun_errno(libc::EAGAIN), XrunAction::Retry);
+    }
+
+    #[test]
+    fn classify_xrun_errno_epipe_and_estrpipe_are_recover() {
+        assert_eq!(classify_xrun_errno(libc::EPIPE), XrunAction::Recover);
+        assert_eq!(classify_xrun_errno(libc::ESTRPIPE), XrunAction::Recover);
+    }
+
+    #[test]
+    fn classify_xrun_errno_other_is_fatal() {
+        assert_eq!(classify_xrun_errno(libc::EINVAL), XrunAction::Fatal);
+    }
+
+    #[test]
+    fn interest_from_pollfd_prefers_pollin() {
+        let poll_fd = libc::pollfd {
+            fd: 0,
+            events: libc::POLLIN | libc::POLLOUT,
+            revents: 0,
+        };
+        assert_eq!(interest_from_pollfd(&poll_fd), PcmInterest::Readable);
+    }
+
+    #[test]
+    fn interest_from_pollfd_falls_back_to_pollout() {
+        let poll_fd = libc::pollfd {
+            fd: 0,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        assert_eq!(interest_from_pollfd(&poll_fd), PcmInterest::Writable);
+    }
+
+    #[test]
+    fn interest_from_pollfd_falls_back_to_pollerr() {
+        let poll_fd = libc::pollfd {
+            fd: 0,
+            events: libc::POLLERR,
+            revents: 0,
+        };
+        assert_eq!(interest_from_pollfd(&poll_fd), PcmInterest::Error);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown interest")]
+    fn interest_from_pollfd_panics_on_unknown_events() {
+        let poll_fd = libc::pollfd {
+            fd: 0,
+            events: 0,
+            revents: 0,
+        };
+        interest_from_pollfd(&poll_fd);
+    }
+
+    #[test]
+    fn frames_to_samples_multiplies_by_channel_count() {
+        assert_eq!(frames_to_samples(10, 2, usize::MAX), 20);
+    }
+
+    #[test]
+    fn frames_to_samples_caps_at_available_samples() {
+        // 10 frames * 2 channels = 20 samples, but only 15 samples (7 whole
+        // frames) are available, so the result must stay frame-aligned
+        // rather than returning a partial-frame 15.
+        assert_eq!(frames_to_samples(10, 2, 15), 14);
+    }
+
+    #[test]
+    fn frames_to_samples_clamps_negative_frames_to_zero() {
+        assert_eq!(frames_to_samples(-1, 2, 100), 0);
+    }
+}